@@ -0,0 +1,137 @@
+// src/db.rs
+// Optional SQLite-backed history so late joiners and reconnecting clients
+// can backfill what was already said instead of seeing a blank room.
+// Persistence is entirely opt-in: if `DATABASE_URL` isn't set, `Store` is
+// never constructed and rooms behave exactly as before (in-memory only).
+
+use anyhow::Result;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+#[derive(Clone)]
+pub struct Store {
+    pool: SqlitePool,
+}
+
+pub struct SrcSegment {
+    pub seq: i64,
+    pub ts: i64,
+    pub src_text: String,
+}
+
+pub struct Translation {
+    pub seq: i64,
+    pub ts: i64,
+    pub tgt_text: String,
+}
+
+impl Store {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    pub async fn create_room(
+        &self,
+        room_id: &str,
+        name: Option<&str>,
+        created_at: i64,
+    ) -> Result<()> {
+        sqlx::query("INSERT INTO rooms (room_id, name, created_at) VALUES (?1, ?2, ?3)")
+            .bind(room_id)
+            .bind(name)
+            .bind(created_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn insert_segment(
+        &self,
+        room_id: &str,
+        seq: i64,
+        ts: i64,
+        src_text: &str,
+    ) -> Result<()> {
+        sqlx::query("INSERT INTO segments (room_id, seq, ts, src_text) VALUES (?1, ?2, ?3, ?4)")
+            .bind(room_id)
+            .bind(seq)
+            .bind(ts)
+            .bind(src_text)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn insert_translation(
+        &self,
+        room_id: &str,
+        seq: i64,
+        lang: &str,
+        ts: i64,
+        tgt_text: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO translations (room_id, seq, lang, ts, tgt_text) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(room_id)
+        .bind(seq)
+        .bind(lang)
+        .bind(ts)
+        .bind(tgt_text)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Source transcript history, oldest first, for a late-joining viewer.
+    pub async fn src_history(
+        &self,
+        room_id: &str,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<SrcSegment>> {
+        let rows = sqlx::query_as::<_, (i64, i64, String)>(
+            "SELECT seq, ts, src_text FROM segments \
+             WHERE room_id = ?1 AND seq > ?2 ORDER BY seq ASC LIMIT ?3",
+        )
+        .bind(room_id)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(seq, ts, src_text)| SrcSegment { seq, ts, src_text })
+            .collect())
+    }
+
+    // Translation history for one target language, oldest first.
+    pub async fn translation_history(
+        &self,
+        room_id: &str,
+        lang: &str,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<Translation>> {
+        let rows = sqlx::query_as::<_, (i64, i64, String)>(
+            "SELECT seq, ts, tgt_text FROM translations \
+             WHERE room_id = ?1 AND lang = ?2 AND seq > ?3 ORDER BY seq ASC LIMIT ?4",
+        )
+        .bind(room_id)
+        .bind(lang)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(seq, ts, tgt_text)| Translation { seq, ts, tgt_text })
+            .collect())
+    }
+}
@@ -10,13 +10,15 @@ LinkedIn : https://id.linkedin.com/in/kukuhtw
 
 */
 
+mod db;
+
 use std::{
     convert::Infallible,
     env,
     net::SocketAddr,
+    sync::atomic::{AtomicBool, AtomicI64, Ordering},
     sync::Arc,
-    sync::atomic::{AtomicBool, Ordering},
-    time::{Duration, Instant},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
@@ -26,24 +28,26 @@ use axum::{
         Path, State, WebSocketUpgrade,
     },
     http::StatusCode,
-    response::{Html, IntoResponse},
     response::sse::{Event, KeepAlive, Sse},
+    response::{Html, IntoResponse},
     routing::{get, post},
     Json, Router,
 };
 use base64::Engine as _;
 use dashmap::DashMap;
 use futures::{SinkExt, StreamExt};
+use rustls::crypto::{ring, CryptoProvider};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tokio::{
     net::TcpListener,
-    sync::{broadcast::{self, Sender}, Mutex},
+    sync::{
+        broadcast::{self, Sender},
+        Mutex,
+    },
 };
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_tungstenite::tungstenite::{self, handshake::client::generate_key};
-use rustls::crypto::{CryptoProvider, ring};
-
 
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -55,32 +59,133 @@ use tracing_subscriber::EnvFilter;
 
 use uuid::Uuid;
 
-
-use tracing::{error, info};
-use tokio::time::timeout;
 use axum::http::HeaderMap;
+use tokio::time::timeout;
+use tracing::{error, info};
 
+// helper untuk log header tanpa bocor token
+fn redact_headers(h: &HeaderMap) -> Vec<(String, String)> {
+    h.iter()
+        .map(|(k, v)| {
+            let ks = k.as_str().to_string();
+            let vs = v.to_str().unwrap_or("<bin>").to_string();
+            let val = if ks.eq_ignore_ascii_case("authorization") {
+                "<redacted>".to_string()
+            } else {
+                vs
+            };
+            (ks, val)
+        })
+        .collect()
+}
 
+// Opaque bearer tokens handed to clients; only their SHA-256 hash is kept
+// server-side so a leaked log/DB dump doesn't hand out room access.
+fn gen_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
 
+fn hash_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(token.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-// helper untuk log header tanpa bocor token
-fn redact_headers(h: &HeaderMap) -> Vec<(String,String)> {
-    h.iter().map(|(k,v)| {
-        let ks = k.as_str().to_string();
-        let vs = v.to_str().unwrap_or("<bin>").to_string();
-        let val = if ks.eq_ignore_ascii_case("authorization") {
-            "<redacted>".to_string()
-        } else { vs };
-        (ks, val)
-    }).collect()
+// Accepts the token as `Authorization: Bearer <token>` or `?token=<token>`.
+fn extract_token(headers: &HeaderMap, query_token: Option<String>) -> Option<String> {
+    if let Some(h) = headers.get(axum::http::header::AUTHORIZATION) {
+        if let Ok(s) = h.to_str() {
+            if let Some(t) = s.strip_prefix("Bearer ") {
+                return Some(t.to_string());
+            }
+        }
+    }
+    query_token
+}
+
+fn authorized(headers: &HeaderMap, query_token: Option<String>, expected_hash: &str) -> bool {
+    match extract_token(headers, query_token) {
+        Some(t) => hash_token(&t) == expected_hash,
+        None => false,
+    }
+}
+
+// A room now carries the raw source transcript stream plus one derived
+// translation stream per distinct target language that a viewer has asked
+// for. Viewers on the source stream see `{src, final}` segments; viewers
+// on a `lang` stream see `{type:"final", lang, text}` translations.
+#[derive(Clone)]
+struct RoomState {
+    src_tx: Sender<String>,
+    targets: Arc<DashMap<String, Arc<TargetStream>>>,
+    // Monotonic per-room sequence number stamped on every finalized source
+    // segment, shared with its translations so history can be replayed and
+    // resumed with `?since=`.
+    next_seq: Arc<AtomicI64>,
+    // SHA-256 hex digests of the speaker/viewer tokens handed out by
+    // `create_room`. We never keep the raw tokens server-side.
+    speaker_token_hash: String,
+    viewer_token_hash: String,
+    // Signaled by the revoke endpoint to tear the room down: every task
+    // reading from this room (speaker socket, translation workers) selects
+    // on it and exits.
+    close: Arc<tokio::sync::Notify>,
+    created_at: i64,
+    // Bumped on every transcription delta/final, so `/api/room/:id` can
+    // report how long a room has been idle.
+    last_activity: Arc<AtomicI64>,
+    // Set while a speaker is connected, so `/api/room/:id/cancel` has a
+    // live channel into the upstream supervisor to inject a cancel.
+    speaker_cmd: Arc<Mutex<Option<tokio::sync::mpsc::Sender<UpstreamCmd>>>>,
+}
+
+// One per distinct target language a room has been asked for. `audio` is
+// opt-in: switched on the first time a viewer subscribes to that language
+// with `?audio=1`, after which the worker also synthesizes and broadcasts
+// spoken audio for every translated segment. `voice` is taken from the most
+// recent such subscriber that passes `&voice=`.
+struct TargetStream {
+    tx: Sender<String>,
+    audio: AtomicBool,
+    voice: Mutex<String>,
 }
 
 #[derive(Clone)]
 struct AppState {
-    rooms: Arc<DashMap<String, Sender<String>>>,
+    rooms: Arc<DashMap<String, RoomState>>,
+    http: reqwest::Client,
     base_url: String,
     api_key: String,
     model: String,
+    // Some when `DATABASE_URL` is set; history/backfill is a no-op otherwise.
+    persist: Option<Arc<db::Store>>,
+    // Some when `ADMIN_TOKEN` is set; gates the cross-room `/api/rooms`
+    // listing. Unset means that endpoint stays disabled rather than open.
+    admin_token_hash: Option<String>,
+}
+
+fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// Adds up to +/-25% jitter to a backoff duration, derived from the clock
+// rather than a `rand` dependency since this is the only place that needs
+// randomness.
+fn jittered(d: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let spread = d.as_millis() as i64 / 4;
+    if spread == 0 {
+        return d;
+    }
+    let offset = (nanos as i64 % (2 * spread + 1)) - spread;
+    let millis = (d.as_millis() as i64 + offset).max(0) as u64;
+    Duration::from_millis(millis)
 }
 
 #[allow(dead_code)]
@@ -93,6 +198,9 @@ struct CreateRoomReq {
 struct CreateRoomResp {
     room_id: String,
     share_url: String,
+    // Secret: keep this on the host. Required to speak (open /ws) and to
+    // revoke the room. Never embedded in share_url.
+    speaker_token: String,
 }
 
 #[tokio::main]
@@ -100,84 +208,727 @@ async fn main() -> Result<()> {
     dotenvy::dotenv().ok();
 
     CryptoProvider::install_default(ring::default_provider())
-    .expect("install rustls ring provider");
+        .expect("install rustls ring provider");
 
     let filter = EnvFilter::try_from_default_env()
-    .unwrap_or_else(|_| EnvFilter::new("info,axum=info,tower_http=info,live_translate=debug"));
+        .unwrap_or_else(|_| EnvFilter::new("info,axum=info,tower_http=info,live_translate=debug"));
 
     tracing_subscriber::fmt()
-    .with_env_filter(filter)
-    .with_max_level(tracing::Level::DEBUG)
-    .with_target(false)
-    .with_writer(std::io::stdout)
-    .init();
+        .with_env_filter(filter)
+        .with_max_level(tracing::Level::DEBUG)
+        .with_target(false)
+        .with_writer(std::io::stdout)
+        .init();
 
-tracing::info!("tracing initialized ✅");
+    tracing::info!("tracing initialized ✅");
 
     let api_key = env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY is required");
-    let model = env::var("REALTIME_MODEL").unwrap_or_else(|_| "gpt-4o-realtime-preview".to_string());
+    let model =
+        env::var("REALTIME_MODEL").unwrap_or_else(|_| "gpt-4o-realtime-preview".to_string());
     let base_url = env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
-    let port: u16 = env::var("PORT").ok().and_then(|s| s.parse().ok()).unwrap_or(8080);
+    let port: u16 = env::var("PORT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(8080);
+
+    let persist = match env::var("DATABASE_URL") {
+        Ok(url) => {
+            let store = db::Store::connect(&url)
+                .await
+                .expect("connect to DATABASE_URL");
+            info!("transcript history enabled ({})", url);
+            Some(Arc::new(store))
+        }
+        Err(_) => {
+            info!("DATABASE_URL not set, running with in-memory history only");
+            None
+        }
+    };
+
+    let admin_token_hash = match env::var("ADMIN_TOKEN") {
+        Ok(t) => {
+            info!("admin endpoints enabled");
+            Some(hash_token(&t))
+        }
+        Err(_) => {
+            info!("ADMIN_TOKEN not set, /api/rooms stays disabled");
+            None
+        }
+    };
 
     let state = AppState {
         rooms: Arc::new(DashMap::new()),
+        http: reqwest::Client::new(),
         base_url,
         api_key,
         model,
+        admin_token_hash,
+        persist,
     };
 
     let app = Router::new()
-        .route("/", get(|| async { Html(include_str!("../static/index.html")) }))
-        .route("/view", get(|| async { Html(include_str!("../static/view.html")) }))
+        .route(
+            "/",
+            get(|| async { Html(include_str!("../static/index.html")) }),
+        )
+        .route(
+            "/view",
+            get(|| async { Html(include_str!("../static/view.html")) }),
+        )
         .route("/api/room", post(create_room))
+        .route("/api/room/:id/revoke", post(revoke_room))
+        .route("/api/rooms", get(list_rooms))
+        .route("/api/room/:id", get(room_detail))
+        .route("/api/room/:id/cancel", post(cancel_room))
         .route("/sse/:room", get(sse_room))
         .route("/ws/:room", get(ws_speaker))
         .nest_service("/static", ServeDir::new("static"))
         .layer(TraceLayer::new_for_http())
-        .layer(CorsLayer::new().allow_methods(Any).allow_headers(Any).allow_origin(Any))
-        .with_state(state);
+        .layer(
+            CorsLayer::new()
+                .allow_methods(Any)
+                .allow_headers(Any)
+                .allow_origin(Any),
+        )
+        .with_state(state.clone());
 
     let addr: SocketAddr = ([0, 0, 0, 0], port).into();
     let listener = TcpListener::bind(addr).await?;
     println!("Open http://127.0.0.1:{port}");
     info!("listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(state))
+        .await?;
     Ok(())
 }
 
+// Waits for Ctrl+C or SIGTERM, then closes every active room so connected
+// viewers get a clean `{"type":"closed"}` event and speaker sockets get a
+// Close frame instead of the connection just vanishing when the process exits.
+async fn shutdown_signal(state: AppState) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    info!(
+        "shutdown signal received, draining {} room(s)",
+        state.rooms.len()
+    );
+    for entry in state.rooms.iter() {
+        close_room(entry.value());
+    }
+}
+
 async fn create_room(
     State(state): State<AppState>,
-    Json(_req): Json<CreateRoomReq>,
+    Json(req): Json<CreateRoomReq>,
 ) -> Json<CreateRoomResp> {
     let room_id = Uuid::new_v4().to_string();
-    let (tx, _rx) = broadcast::channel::<String>(256);
-    state.rooms.insert(room_id.clone(), tx);
+    let speaker_token = gen_token();
+    let viewer_token = gen_token();
+    let (src_tx, _rx) = broadcast::channel::<String>(256);
+    let created_at = now_unix();
+    state.rooms.insert(
+        room_id.clone(),
+        RoomState {
+            src_tx,
+            targets: Arc::new(DashMap::new()),
+            next_seq: Arc::new(AtomicI64::new(0)),
+            speaker_token_hash: hash_token(&speaker_token),
+            viewer_token_hash: hash_token(&viewer_token),
+            close: Arc::new(tokio::sync::Notify::new()),
+            created_at,
+            last_activity: Arc::new(AtomicI64::new(created_at)),
+            speaker_cmd: Arc::new(Mutex::new(None)),
+        },
+    );
+
+    if let Some(store) = &state.persist {
+        if let Err(e) = store
+            .create_room(&room_id, req.name.as_deref(), created_at)
+            .await
+        {
+            error!("failed to persist room {}: {:?}", room_id, e);
+        }
+    }
 
-    let share_url = format!("{}/view?room={}", state.base_url, room_id);
-    info!("room created {} -> {}", room_id, share_url);
-    Json(CreateRoomResp { room_id, share_url })
+    let share_url = format!(
+        "{}/view?room={}&token={}",
+        state.base_url, room_id, viewer_token
+    );
+    info!("room created {}", room_id);
+    Json(CreateRoomResp {
+        room_id,
+        share_url,
+        speaker_token,
+    })
 }
 
-async fn sse_room(Path(room): Path<String>, State(state): State<AppState>) -> impl IntoResponse {
-    let tx = match state.rooms.get(&room) {
-        Some(t) => t.clone(),
+#[derive(Deserialize)]
+struct RevokeParams {
+    token: Option<String>,
+}
+
+// Tears a room down: removes it so nobody can join it anymore, and wakes
+// every task still serving it (speaker socket, translation workers) so
+// existing connections close instead of lingering.
+async fn revoke_room(
+    Path(room): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<RevokeParams>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let room_state = match state.rooms.get(&room) {
+        Some(r) => r.clone(),
         None => return (StatusCode::NOT_FOUND, "room not found").into_response(),
     };
+    if !authorized(&headers, params.token, &room_state.speaker_token_hash) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing speaker token").into_response();
+    }
+
+    state.rooms.remove(&room);
+    close_room(&room_state);
+    info!("room revoked {}", room);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+// Wakes every task serving this room and tells subscribers it's gone.
+// Shared by the revoke endpoint and graceful shutdown.
+fn close_room(room_state: &RoomState) {
+    room_state.close.notify_waiters();
+    let _ = room_state.src_tx.send(json!({"type":"closed"}).to_string());
+    for entry in room_state.targets.iter() {
+        let _ = entry.value().tx.send(json!({"type":"closed"}).to_string());
+    }
+}
+
+// `src_tx.receiver_count()` also counts one subscription per running
+// `translation_worker` (it reads the source stream to know what to
+// translate), so it overstates how many viewers are actually watching the
+// raw source feed. Subtract the worker count to get the real figure.
+fn src_viewer_count(room_state: &RoomState) -> usize {
+    room_state
+        .src_tx
+        .receiver_count()
+        .saturating_sub(room_state.targets.len())
+}
+
+#[derive(Serialize)]
+struct RoomSummary {
+    room_id: String,
+    created_at: i64,
+    last_activity: i64,
+    src_subscribers: usize,
+    target_langs: usize,
+}
+
+// `GET /api/rooms` — operator-facing list of every active room, including
+// its UUID. Gated behind `ADMIN_TOKEN` (same bearer/`?token=` convention as
+// the per-room tokens) since listing room ids would otherwise defeat the
+// speaker/viewer token scheme in one request; if `ADMIN_TOKEN` isn't set,
+// the endpoint is disabled rather than left open.
+async fn list_rooms(
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<RevokeParams>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let Some(admin_hash) = &state.admin_token_hash else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "admin endpoints disabled; set ADMIN_TOKEN to enable",
+        )
+            .into_response();
+    };
+    if !authorized(&headers, params.token, admin_hash) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing admin token").into_response();
+    }
 
-    let rx = tx.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|msg| async move {
+    let rooms: Vec<RoomSummary> = state
+        .rooms
+        .iter()
+        .map(|entry| {
+            let r = entry.value();
+            RoomSummary {
+                room_id: entry.key().clone(),
+                created_at: r.created_at,
+                last_activity: r.last_activity.load(Ordering::Relaxed),
+                src_subscribers: src_viewer_count(r),
+                target_langs: r.targets.len(),
+            }
+        })
+        .collect();
+    Json(rooms).into_response()
+}
+
+#[derive(Serialize)]
+struct TargetSummary {
+    lang: String,
+    subscribers: usize,
+    audio: bool,
+}
+
+#[derive(Serialize)]
+struct RoomDetail {
+    room_id: String,
+    created_at: i64,
+    last_activity: i64,
+    src_subscribers: usize,
+    speaker_connected: bool,
+    targets: Vec<TargetSummary>,
+}
+
+// `GET /api/room/:id` — per-room detail, including each target language's
+// subscriber count and whether spoken audio is enabled for it. Accepts
+// either that room's speaker or viewer token.
+async fn room_detail(
+    Path(room): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<RevokeParams>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let room_state = match state.rooms.get(&room) {
+        Some(r) => r.clone(),
+        None => return (StatusCode::NOT_FOUND, "room not found").into_response(),
+    };
+    let authed = authorized(
+        &headers,
+        params.token.clone(),
+        &room_state.speaker_token_hash,
+    ) || authorized(&headers, params.token, &room_state.viewer_token_hash);
+    if !authed {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing token").into_response();
+    }
+
+    let targets = room_state
+        .targets
+        .iter()
+        .map(|entry| TargetSummary {
+            lang: entry.key().clone(),
+            subscribers: entry.value().tx.receiver_count(),
+            audio: entry.value().audio.load(Ordering::Relaxed),
+        })
+        .collect();
+
+    Json(RoomDetail {
+        room_id: room,
+        created_at: room_state.created_at,
+        last_activity: room_state.last_activity.load(Ordering::Relaxed),
+        src_subscribers: src_viewer_count(&room_state),
+        speaker_connected: room_state.speaker_cmd.lock().await.is_some(),
+        targets,
+    })
+    .into_response()
+}
+
+// `POST /api/room/:id/cancel` — injects `response.cancel` into the room's
+// upstream connection, e.g. to abort an over-long generation on demand.
+// Requires the speaker token, same as `revoke_room`: this can disrupt a
+// live session, so only the host should be able to trigger it.
+async fn cancel_room(
+    Path(room): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<RevokeParams>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let room_state = match state.rooms.get(&room) {
+        Some(r) => r.clone(),
+        None => return (StatusCode::NOT_FOUND, "room not found").into_response(),
+    };
+    if !authorized(&headers, params.token, &room_state.speaker_token_hash) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing speaker token").into_response();
+    }
+
+    match room_state.speaker_cmd.lock().await.as_ref() {
+        Some(cmd_tx) => {
+            let _ = cmd_tx.send(UpstreamCmd::Cancel).await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => (StatusCode::CONFLICT, "no speaker connected").into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SseParams {
+    lang: Option<String>,
+    // Opt into a spoken track for this language: `?lang=en&audio=1&voice=alloy`.
+    // Kept as a string so both `audio=1` and `audio=true` are accepted.
+    audio: Option<String>,
+    voice: Option<String>,
+    // Backfill: replay history with seq > `since` (default 0), up to `limit`
+    // segments (default DEFAULT_HISTORY_LIMIT), before joining the live stream.
+    since: Option<i64>,
+    limit: Option<i64>,
+    // Viewer token, as handed out in `share_url`. Can also be sent as
+    // `Authorization: Bearer <token>`.
+    token: Option<String>,
+}
+
+const DEFAULT_VOICE: &str = "alloy";
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+
+async fn sse_room(
+    Path(room): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<SseParams>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let room_state = match state.rooms.get(&room) {
+        Some(r) => r.clone(),
+        None => return (StatusCode::NOT_FOUND, "room not found").into_response(),
+    };
+
+    if !authorized(
+        &headers,
+        params.token.clone(),
+        &room_state.viewer_token_hash,
+    ) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing viewer token").into_response();
+    }
+
+    let since = params.since.unwrap_or(0);
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+    // Subscribe before reading backfill, not after: otherwise anything
+    // broadcast in the gap between the history query and the subscribe
+    // call is neither in the snapshot nor delivered live. `last_seq` below
+    // then lets the live stream drop whatever the backfill already covered.
+    let (rx, lang_for_history) = match params.lang.clone() {
+        None => (room_state.src_tx.subscribe(), None),
+        Some(lang) => {
+            let target = ensure_translation_stream(&state, &room, &room_state, &lang);
+            if matches!(params.audio.as_deref(), Some("1") | Some("true")) {
+                if let Some(voice) = params.voice {
+                    *target.voice.lock().await = voice;
+                }
+                target.audio.store(true, Ordering::SeqCst);
+            }
+            (target.tx.subscribe(), Some(lang))
+        }
+    };
+
+    let (backfill, last_seq) =
+        history_events(&state, &room, lang_for_history.as_deref(), since, limit).await;
+
+    let backfill_stream = tokio_stream::iter(backfill.into_iter().map(Ok::<_, Infallible>));
+    let live_stream = BroadcastStream::new(rx).filter_map(move |msg| async move {
         match msg {
-            Ok(s) => Some(Ok::<_, Infallible>(Event::default().data(s))),
+            Ok(s) => {
+                // Only `final` events share the transcript/translation `seq`
+                // namespace the backfill was drawn from — `audio` frames carry
+                // an unrelated per-worker chunk counter that also starts at 0,
+                // so never dedupe those against `last_seq`.
+                let v = serde_json::from_str::<Value>(&s).ok();
+                let is_final = v
+                    .as_ref()
+                    .and_then(|v| v.get("type"))
+                    .and_then(|t| t.as_str())
+                    == Some("final");
+                if is_final {
+                    let seq = v.and_then(|v| v.get("seq").and_then(|x| x.as_i64()));
+                    if seq.map_or(false, |seq| seq <= last_seq) {
+                        return None;
+                    }
+                }
+                Some(Ok::<_, Infallible>(Event::default().data(s)))
+            }
             Err(_) => None,
         }
     });
 
-    Sse::new(stream)
+    Sse::new(backfill_stream.chain(live_stream))
         .keep_alive(KeepAlive::default())
         .into_response()
 }
 
+// Loads persisted history (if persistence is enabled) as already-formatted
+// SSE `Event`s, in the same shapes the live streams use, so late joiners
+// and reconnecting clients catch up before the live broadcast takes over.
+// Also returns the highest `seq` included, so the caller can drop any live
+// event the backfill already delivered instead of double-sending it.
+async fn history_events(
+    state: &AppState,
+    room: &str,
+    lang: Option<&str>,
+    since: i64,
+    limit: i64,
+) -> (Vec<Event>, i64) {
+    let Some(store) = &state.persist else {
+        return (Vec::new(), since);
+    };
+
+    match lang {
+        None => match store.src_history(room, since, limit).await {
+            Ok(rows) => {
+                let last_seq = rows.last().map(|s| s.seq).unwrap_or(since);
+                let events = rows
+                    .into_iter()
+                    .map(|s| {
+                        Event::default()
+                            .data(json!({"type":"final","seq":s.seq,"src":s.src_text}).to_string())
+                    })
+                    .collect();
+                (events, last_seq)
+            }
+            Err(e) => {
+                error!("failed to load src history room={}: {:?}", room, e);
+                (Vec::new(), since)
+            }
+        },
+        Some(lang) => match store.translation_history(room, lang, since, limit).await {
+            Ok(rows) => {
+                let last_seq = rows.last().map(|t| t.seq).unwrap_or(since);
+                let events = rows
+                    .into_iter()
+                    .map(|t| {
+                        Event::default().data(
+                            json!({"type":"final","lang":lang,"seq":t.seq,"text":t.tgt_text})
+                                .to_string(),
+                        )
+                    })
+                    .collect();
+                (events, last_seq)
+            }
+            Err(e) => {
+                error!(
+                    "failed to load translation history room={} lang={}: {:?}",
+                    room, lang, e
+                );
+                (Vec::new(), since)
+            }
+        },
+    }
+}
+
+// Returns the target stream for `lang`, creating it (and its translation
+// worker) the first time anyone asks for that language in this room.
+fn ensure_translation_stream(
+    state: &AppState,
+    room: &str,
+    room_state: &RoomState,
+    lang: &str,
+) -> Arc<TargetStream> {
+    if let Some(t) = room_state.targets.get(lang) {
+        return t.clone();
+    }
+
+    let (tgt_tx, _rx) = broadcast::channel::<String>(256);
+    let target = Arc::new(TargetStream {
+        tx: tgt_tx,
+        audio: AtomicBool::new(false),
+        voice: Mutex::new(DEFAULT_VOICE.to_string()),
+    });
+    room_state.targets.insert(lang.to_string(), target.clone());
+    info!("spawning translation worker room={} lang={}", room, lang);
+    tokio::spawn(translation_worker(
+        state.clone(),
+        room.to_string(),
+        lang.to_string(),
+        room_state.src_tx.subscribe(),
+        target.clone(),
+        room_state.close.clone(),
+    ));
+    target
+}
+
+// Consumes finalized `{src, final}` segments off the room's source stream
+// and pushes `{type:"final", lang, text}` translations onto the
+// per-language stream, followed by `{type:"audio", lang, seq, chunk}`
+// frames if a viewer has opted that language into spoken output. One of
+// these runs per distinct target language a room has ever been asked for.
+async fn translation_worker(
+    state: AppState,
+    room: String,
+    lang: String,
+    mut src_rx: broadcast::Receiver<String>,
+    target: Arc<TargetStream>,
+    close: Arc<tokio::sync::Notify>,
+) {
+    let mut audio_seq: u64 = 0;
+    // Created once, so a close signaled while a translate/TTS call is in
+    // flight is still seen on the next pass through the loop.
+    let closed = close.notified();
+    tokio::pin!(closed);
+
+    loop {
+        let msg = tokio::select! {
+            biased;
+            _ = &mut closed => break,
+            r = src_rx.recv() => match r {
+                Ok(m) => m,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        let v: Value = match serde_json::from_str(&msg) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if v.get("type").and_then(|t| t.as_str()) != Some("final") {
+            continue;
+        }
+        let src_text = match v.get("src").and_then(|s| s.as_str()) {
+            Some(s) if !s.is_empty() => s,
+            _ => continue,
+        };
+        let seq = v.get("seq").and_then(|s| s.as_i64()).unwrap_or(0);
+
+        // Nobody's listening on this language right now — skip the translate
+        // (and TTS) call rather than keep paying for it for the rest of the
+        // room's life every time the last subscriber for `lang` disconnects.
+        if target.tx.receiver_count() == 0 {
+            continue;
+        }
+
+        let text = match translate_text(&state, src_text, &lang).await {
+            Ok(text) => text,
+            Err(e) => {
+                error!("translation failed room={} lang={}: {:?}", room, lang, e);
+                let _ = target.tx.send(
+                    json!({"type":"error","lang":lang,"data":{"message": e.to_string()}})
+                        .to_string(),
+                );
+                continue;
+            }
+        };
+        let _ = target
+            .tx
+            .send(json!({"type":"final","lang":lang,"seq":seq,"text":text}).to_string());
+
+        if let Some(store) = &state.persist {
+            if let Err(e) = store
+                .insert_translation(&room, seq, &lang, now_unix(), &text)
+                .await
+            {
+                error!(
+                    "failed to persist translation room={} lang={}: {:?}",
+                    room, lang, e
+                );
+            }
+        }
+
+        if target.audio.load(Ordering::SeqCst) {
+            let voice = target.voice.lock().await.clone();
+            match synthesize_speech(&state, &text, &voice).await {
+                Ok(pcm) => {
+                    for chunk in pcm.chunks(AUDIO_CHUNK_BYTES) {
+                        let b64 = base64::engine::general_purpose::STANDARD.encode(chunk);
+                        audio_seq += 1;
+                        let _ = target.tx.send(
+                            json!({"type":"audio","lang":lang,"seq":audio_seq,"chunk":b64})
+                                .to_string(),
+                        );
+                    }
+                }
+                Err(e) => error!("tts failed room={} lang={}: {:?}", room, lang, e),
+            }
+        }
+    }
+}
+
+// Bounded so a single SSE frame stays comfortably under typical proxy/
+// browser limits once base64-inflated (~44KB as text).
+const AUDIO_CHUNK_BYTES: usize = 32 * 1024;
+
+// Re-scoped from the original "ask the Realtime session for `response.audio.delta`"
+// design: after the per-viewer-language fan-out (see the `RoomState`/`TargetStream`
+// comments above), the Realtime session only ever produces a transcript — it has no
+// `response` to attach audio modalities to, and a single upstream session couldn't
+// speak N different target languages to N viewers anyway. So `want_audio`/`voice`
+// live on `sse_room`'s query params (`?lang=en&audio=1&voice=alloy`) instead of
+// `ClientMsg::Init`, and audio is produced here with one REST call per finalized
+// translation rather than a streamed upstream track. That's an extra per-segment
+// OpenAI call (added latency + cost per translated segment, on top of the
+// transcription + translation calls), worth knowing before enabling `audio=1`
+// on a busy room.
+//
+// Synthesizes `text` as 24kHz mono 16-bit PCM (same format the browser's
+// `AudioContext` already expects for recorded input), so the client can
+// decode speaker and translated audio the same way.
+async fn synthesize_speech(state: &AppState, text: &str, voice: &str) -> Result<Vec<u8>> {
+    let body = json!({
+        "model": "gpt-4o-mini-tts",
+        "voice": voice,
+        "input": text,
+        "response_format": "pcm",
+    });
+
+    let resp = state
+        .http
+        .post("https://api.openai.com/v1/audio/speech")
+        .bearer_auth(&state.api_key)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(resp.bytes().await?.to_vec())
+}
+
+fn lang_label(code: &str) -> &'static str {
+    match code {
+        "id" => "Indonesian",
+        "en" => "English",
+        "ja" => "日本語",
+        "ko" => "한국어",
+        "ar" => "العربية",
+        "de" => "Deutsch",
+        "fr" => "Français",
+        "nl" => "Nederlands",
+        "ru" => "Русский",
+        "es" => "Español",
+        _ => "English",
+    }
+}
+
+async fn translate_text(state: &AppState, text: &str, target_lang: &str) -> Result<String> {
+    let tgt = lang_label(target_lang);
+    let body = json!({
+        "model": "gpt-4o-mini",
+        "messages": [{
+            "role": "user",
+            "content": format!(
+                "Translate the following into {tgt} only. Respond with the translation only, no quotes, no explanation.\n\n{text}"
+            )
+        }],
+        "temperature": 0.2,
+    });
+
+    let resp = state
+        .http
+        .post("https://api.openai.com/v1/chat/completions")
+        .bearer_auth(&state.api_key)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let v: Value = resp.json().await?;
+    Ok(v["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap_or("")
+        .trim()
+        .to_string())
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "type")]
 enum ClientMsg {
@@ -187,67 +938,70 @@ enum ClientMsg {
     Commit,
 }
 
+#[derive(Deserialize)]
+struct WsAuthParams {
+    token: Option<String>,
+}
+
 async fn ws_speaker(
     ws: WebSocketUpgrade,
     Path(room): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<WsAuthParams>,
+    headers: HeaderMap,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
+    let room_state = match state.rooms.get(&room) {
+        Some(r) => r.clone(),
+        None => return (StatusCode::NOT_FOUND, "room not found").into_response(),
+    };
+
+    if !authorized(&headers, params.token, &room_state.speaker_token_hash) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing speaker token").into_response();
+    }
+
     ws.on_upgrade(move |socket| handle_ws(socket, room, state))
+        .into_response()
 }
 
-fn json_instr(src_name: &str, tgt_label_native: &str, name: &str) -> String {
-    format!(
-      "You are a real-time translator for {name}. \
-       First transcribe in {src}. Then translate to {tgt} only. \
-       Respond EXACTLY one JSON: {{\"src\":\"<{src} transcript>\",\"tgt\":\"<{tgt} translation>\"}}. \
-       If {tgt} is 日本語, use Japanese script (かな/漢字), no romaji, no English.",
-      src = src_name, tgt = tgt_label_native, name = name
-    )
-}
-fn instructions_for(pair: &str, name: &str) -> (&'static str, String) {
-    match pair {
-        // existing
-        "id-ja" => ("id", json_instr("Indonesian", "日本語", name)),
-        "ja-id" => ("ja", json_instr("日本語", "Indonesian", name)),
-        "id-en" => ("id", json_instr("Indonesian", "English", name)),
-        "en-id" => ("en", json_instr("English", "Indonesian", name)),
-
-        // Korean
-        "id-ko" => ("id", json_instr("Indonesian", "한국어", name)),
-        "ko-id" => ("ko", json_instr("한국어", "Indonesian", name)),
-
-        // Arabic
-        "id-ar" => ("id", json_instr("Indonesian", "العربية", name)),
-        "ar-id" => ("ar", json_instr("العربية", "Indonesian", name)),
-
-        // German
-        "id-de" => ("id", json_instr("Indonesian", "Deutsch", name)),
-        "de-id" => ("de", json_instr("Deutsch", "Indonesian", name)),
-
-        // French
-        "id-fr" => ("id", json_instr("Indonesian", "Français", name)),
-        "fr-id" => ("fr", json_instr("Français", "Indonesian", name)),
-
-        // Dutch
-        "id-nl" => ("id", json_instr("Indonesian", "Nederlands", name)),
-        "nl-id" => ("nl", json_instr("Nederlands", "Indonesian", name)),
-
-        // Russian
-        "id-ru" => ("id", json_instr("Indonesian", "Русский", name)),
-        "ru-id" => ("ru", json_instr("Русский", "Indonesian", name)),
-
-        // Spanish
-        "id-es" => ("id", json_instr("Indonesian", "Español", name)),
-        "es-id" => ("es", json_instr("Español", "Indonesian", name)),
-
-        _ => ("id", json_instr("Indonesian", "English", name)),
+// The speaker only picks which language they're speaking; translation to
+// each viewer's target now happens out-of-band in `translation_worker`.
+// `pair` is kept as the wire format (e.g. "id-en") for compatibility with
+// the existing client, but only the source half is used here.
+fn src_lang_for(pair: &str) -> &'static str {
+    match pair.split('-').next().unwrap_or("id") {
+        "id" => "id",
+        "ja" => "ja",
+        "en" => "en",
+        "ko" => "ko",
+        "ar" => "ar",
+        "de" => "de",
+        "fr" => "fr",
+        "nl" => "nl",
+        "ru" => "ru",
+        "es" => "es",
+        _ => "id",
     }
 }
 
+// Messages the speaker's websocket task hands to the upstream supervisor.
+// Kept separate from the upstream connection itself so a mid-session
+// reconnect doesn't require tearing down the browser socket.
+enum UpstreamCmd {
+    Init { src_lang: &'static str },
+    Audio(Vec<u8>),
+    Commit,
+    Cancel,
+}
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+// Bounds how much unacknowledged audio we'll replay after a reconnect —
+// enough for a few seconds of speech, not an unbounded memory leak.
+const MAX_PENDING_AUDIO_CHUNKS: usize = 200;
 
 async fn handle_ws(mut socket: WebSocket, room: String, state: AppState) {
-    let tx = match state.rooms.get(&room) {
-        Some(t) => t.clone(),
+    let room_state = match state.rooms.get(&room) {
+        Some(r) => r.clone(),
         None => {
             let _ = socket
                 .send(Message::Text("{\"error\":\"room not found\"}".into()))
@@ -255,234 +1009,74 @@ async fn handle_ws(mut socket: WebSocket, room: String, state: AppState) {
             return;
         }
     };
+    let tx = room_state.src_tx.clone();
     info!("ws client connected for room {}", room);
 
-    // Connect to OpenAI Realtime
-    
-
-   // ==== OpenAI Realtime handshake + logging detail ====
-let url = format!("wss://api.openai.com/v1/realtime?model={}", state.model);
-let key = generate_key();
-let req = axum::http::Request::builder()
-    .method("GET")
-    .uri(&url)
-    .header("Host", "api.openai.com")
-    .header("Upgrade", "websocket")
-    .header("Connection", "Upgrade")
-    .header("Sec-WebSocket-Version", "13")
-    .header("Sec-WebSocket-Key", key)
-   .header("Sec-WebSocket-Protocol", "realtime")
-    // <-- per subprotocol dicoba di sini
-    .header("Authorization", format!("Bearer {}", state.api_key))
-    .header("OpenAI-Beta", "realtime=v1")
-    .body(())
-    .unwrap();
-
-info!("🔌 OpenAI connect → {}", url);
-let hdrs = redact_headers(req.headers());
-info!("🔎 Request headers: {:?}", hdrs);
-
-// beri timeout agar terlihat kalau macet
-
-
-const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
-let res = timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(req)).await;
-
-let (upstream, resp) = match res {
-    Err(_) => {
-        error!("⏱️ upstream connect timeout after {:?} to {}", CONNECT_TIMEOUT, url);
-        let _ = socket
-            .send(Message::Text(serde_json::json!({ "error": "upstream timeout" }).to_string()))
-            .await;
-        let _ = socket.send(Message::Close(None)).await;
-        return;
-    }
-    Ok(Err(e)) => {
-        error!("❌ upstream connect failed: {:?}", e);
-        let msg = format!("upstream connect failed: {e}");
-        let _ = socket
-            .send(Message::Text(serde_json::json!({ "error": msg }).to_string()))
-            .await;
-        let _ = socket.send(Message::Close(None)).await;
-        return;
-    }
-    Ok(Ok(p)) => p,
-};
-
-info!("✅ connected to OpenAI Realtime, status={}", resp.status());
-let rh = redact_headers(resp.headers());
-info!("🔎 Response headers: {:?}", rh);
-
-
-
-    let (mut upstream_write, mut upstream_read) = upstream.split();
-
-    // Shared flags
-    let response_active = Arc::new(AtomicBool::new(false));
-    let last_delta = Arc::new(Mutex::new(Instant::now()));
-    let response_active_r = response_active.clone();
-    let last_delta_r = last_delta.clone();
-
-    // Reader: forward model deltas to SSE
-    let tx_clone = tx.clone();
-    let reader = tokio::spawn(async move {
-        let mut current_buf = String::new();
-
-        while let Some(msg) = upstream_read.next().await {
-            match msg {
-                Ok(tungstenite::Message::Text(txt)) => {
-                    if let Ok(v) = serde_json::from_str::<Value>(&txt) {
-                        let t = v.get("type").and_then(|x| x.as_str()).unwrap_or("-");
-
-                        match t {
-                            "response.created" => {
-                                info!("← response.created");
-                                response_active_r.store(true, Ordering::SeqCst);
-                            }
-                            "response.output_text.delta" | "response.text.delta" => {
-                                if let Some(delta) = v.get("delta").and_then(|x| x.as_str()) {
-                                    *last_delta_r.lock().await = Instant::now();
-                                    current_buf.push_str(delta);
-                                    let _ = tx_clone.send(
-                                        json!({"type":"partial","text": current_buf}).to_string()
-                                    );
-                                }
-                            }
-                            "response.delta" => {
-                                if let Some(d) = v.get("delta") {
-                                    if d.get("type").and_then(|x| x.as_str())
-                                        == Some("output_text.delta")
-                                    {
-                                        if let Some(delta) = d.get("text").and_then(|x| x.as_str()) {
-                                            *last_delta_r.lock().await = Instant::now();
-                                            current_buf.push_str(delta);
-                                            let _ = tx_clone.send(
-                                                json!({"type":"partial","text": current_buf}).to_string()
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            "response.output_text.done"
-                            | "response.completed"
-                            | "response.text.done"
-                            | "response.done" => {
-                                info!("← {}", t);
-                                if !current_buf.is_empty() {
-                                    let _ = tx_clone.send(
-                                        json!({"type":"final","text": current_buf}).to_string()
-                                    );
-                                    current_buf.clear();
-                                }
-                                response_active_r.store(false, Ordering::SeqCst);
-                            }
-                            "error" => {
-                                error!("← error: {}", txt);
-                                let _ = tx_clone.send(json!({"type":"error","data": v}).to_string());
-                                response_active_r.store(false, Ordering::SeqCst);
-                            }
-                            _ => { /* verbose silenced */ }
-                        }
-                    }
-                }
-                Ok(_) => {}
-                Err(e) => {
-                    error!("upstream read error: {}", e);
-                    break;
-                }
-            }
-        }
-    });
+    let (cmd_tx, cmd_rx) = tokio::sync::mpsc::channel::<UpstreamCmd>(256);
+    *room_state.speaker_cmd.lock().await = Some(cmd_tx.clone());
+    let supervisor = tokio::spawn(upstream_supervisor(
+        state.clone(),
+        room.clone(),
+        room_state.clone(),
+        cmd_rx,
+    ));
 
-    // Writer: receive from browser & forward to OpenAI
+    // Writer: receive from browser & hand off to the upstream supervisor.
     let (mut ws_tx, mut ws_rx) = socket.split();
     let mut inited = false;
     let mut audio_buffer_size: usize = 0;
+    let closed = room_state.close.notified();
+    tokio::pin!(closed);
+
+    loop {
+        let msg = tokio::select! {
+            biased;
+            _ = &mut closed => {
+                info!("room {} revoked, disconnecting speaker", room);
+                break;
+            }
+            next = ws_rx.next() => match next {
+                Some(Ok(m)) => m,
+                _ => break,
+            },
+        };
 
-    while let Some(Ok(msg)) = ws_rx.next().await {
         match msg {
             Message::Text(t) => {
                 if let Ok(v) = serde_json::from_str::<ClientMsg>(&t) {
                     match v {
                         ClientMsg::Init { name, pair } => {
-                            let (src_lang, instr) = instructions_for(&pair, &name);
-
-                            // IMPORTANT: disable server VAD to avoid conflicts with manual commit
-                          let session_update = json!({
-    "type": "session.update",
-    "session": {
-        "instructions": instr,
-        "modalities": ["text"],
-        "input_audio_transcription": {
-            "model": "gpt-4o-mini-transcribe",
-            "language": src_lang
-        }
-    }
-});
-                            log_upstream_json("session.update", &session_update);
-                            let _ = upstream_write
-                                .send(tungstenite::Message::Text(session_update.to_string()))
-                                .await;
-
+                            let src_lang = src_lang_for(&pair);
+                            info!("init room={} speaker={} src_lang={}", room, name, src_lang);
+                            let _ = cmd_tx.send(UpstreamCmd::Init { src_lang }).await;
                             inited = true;
                             audio_buffer_size = 0;
                         }
 
                         ClientMsg::Commit => {
-                            if !inited { continue; }
-// Tunggu sebentar untuk memastikan append diproses
-    tokio::time::sleep(Duration::from_millis(100)).await;
-    
-                            // Hitung durasi audio berdasarkan sample rate (default 24kHz)
-    const SAMPLE_RATE: usize = 24000; // Hz
-    const BYTES_PER_SAMPLE: usize = 2; // PCM16 = 2 bytes per sample
-    const MIN_DURATION_MS: usize = 100; // minimal 100ms
-    
-    let min_samples = (SAMPLE_RATE * MIN_DURATION_MS) / 1000;
-    let min_bytes = min_samples * BYTES_PER_SAMPLE;
-    
-    if audio_buffer_size < min_bytes {
-        info!("skip commit: buffer has {}ms (need {}ms)", 
-              (audio_buffer_size * 1000) / (SAMPLE_RATE * BYTES_PER_SAMPLE),
-              MIN_DURATION_MS);
-        continue;
-    }
-                            // If still streaming, cancel stale response (>800ms no delta)
-                            if response_active.load(Ordering::SeqCst) {
-                                let elapsed = last_delta.lock().await.elapsed();
-                                if elapsed > Duration::from_millis(800) {
-                                    let cancel = json!({"type":"response.cancel"});
-                                    let _ = upstream_write
-                                        .send(tungstenite::Message::Text(cancel.to_string()))
-                                        .await;
-                                    response_active.store(false, Ordering::SeqCst);
-                                    info!("response.cancel (stale {:?})", elapsed);
-                                } else {
-                                    info!("skip response.create: still active (last delta {:?})", elapsed);
-                                    continue;
-                                }
+                            if !inited {
+                                continue;
                             }
+                            // Tunggu sebentar untuk memastikan append diproses
+                            tokio::time::sleep(Duration::from_millis(100)).await;
 
-                            let commit = json!({ "type": "input_audio_buffer.commit" });
-                            log_upstream_json("input_audio_buffer.commit", &commit);
-                            let _ = upstream_write
-                                .send(tungstenite::Message::Text(commit.to_string()))
-                                .await;
-
-                           let create = json!({
-  "type": "response.create",
-  "response": {
-    "modalities": ["text"],
-    "conversation": "none",
-    "temperature": 0.6
-  }
-});
-                            log_upstream_json("response.create", &create);
-                            let _ = upstream_write
-                                .send(tungstenite::Message::Text(create.to_string()))
-                                .await;
-
-                            response_active.store(true, Ordering::SeqCst);
+                            // Hitung durasi audio berdasarkan sample rate (default 24kHz)
+                            const SAMPLE_RATE: usize = 24000; // Hz
+                            const BYTES_PER_SAMPLE: usize = 2; // PCM16 = 2 bytes per sample
+                            const MIN_DURATION_MS: usize = 100; // minimal 100ms
+
+                            let min_samples = (SAMPLE_RATE * MIN_DURATION_MS) / 1000;
+                            let min_bytes = min_samples * BYTES_PER_SAMPLE;
+
+                            if audio_buffer_size < min_bytes {
+                                info!(
+                                    "skip commit: buffer has {}ms (need {}ms)",
+                                    (audio_buffer_size * 1000) / (SAMPLE_RATE * BYTES_PER_SAMPLE),
+                                    MIN_DURATION_MS
+                                );
+                                continue;
+                            }
+                            let _ = cmd_tx.send(UpstreamCmd::Commit).await;
                             audio_buffer_size = 0; // reset after commit
                         }
                     }
@@ -490,29 +1084,269 @@ info!("🔎 Response headers: {:?}", rh);
             }
 
             Message::Binary(bin) => {
-                if !inited { continue; }
+                if !inited {
+                    continue;
+                }
 
                 audio_buffer_size += bin.len();
-                 info!("Audio buffer: {} bytes ({}ms)", 
-          audio_buffer_size,
-          (audio_buffer_size * 1000) / (24000 * 2)); // 24kHz, PCM16
+                info!(
+                    "Audio buffer: {} bytes ({}ms)",
+                    audio_buffer_size,
+                    (audio_buffer_size * 1000) / (24000 * 2)
+                ); // 24kHz, PCM16
+
+                let _ = cmd_tx.send(UpstreamCmd::Audio(bin)).await;
+            }
+
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    drop(cmd_tx);
+    *room_state.speaker_cmd.lock().await = None;
+    let _ = ws_tx.send(Message::Close(None)).await;
+    let _ = supervisor.await;
+}
 
+// Connects to the OpenAI Realtime websocket, logging the handshake the
+// same way the original single-shot connect did.
+async fn connect_upstream(
+    state: &AppState,
+) -> Result<(
+    futures::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+        tungstenite::Message,
+    >,
+    futures::stream::SplitStream<
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    >,
+)> {
+    let url = format!("wss://api.openai.com/v1/realtime?model={}", state.model);
+    let key = generate_key();
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(&url)
+        .header("Host", "api.openai.com")
+        .header("Upgrade", "websocket")
+        .header("Connection", "Upgrade")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", key)
+        .header("Sec-WebSocket-Protocol", "realtime")
+        .header("Authorization", format!("Bearer {}", state.api_key))
+        .header("OpenAI-Beta", "realtime=v1")
+        .body(())
+        .unwrap();
+
+    info!("🔌 OpenAI connect → {}", url);
+    info!("🔎 Request headers: {:?}", redact_headers(req.headers()));
+
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    let (upstream, resp) = timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(req))
+        .await
+        .map_err(|_| anyhow::anyhow!("upstream connect timeout after {:?}", CONNECT_TIMEOUT))??;
+
+    info!("✅ connected to OpenAI Realtime, status={}", resp.status());
+    info!("🔎 Response headers: {:?}", redact_headers(resp.headers()));
+
+    Ok(upstream.split())
+}
+
+fn session_update_for(src_lang: &str) -> Value {
+    json!({
+        "type": "session.update",
+        "session": {
+            "modalities": ["text"],
+            "input_audio_transcription": {
+                "model": "gpt-4o-mini-transcribe",
+                "language": src_lang
+            }
+        }
+    })
+}
+
+// Owns the upstream Realtime connection for the lifetime of one speaker
+// session. On any read error or upstream close it reconnects with
+// exponential backoff, replays the last `session.update`, and flushes any
+// audio appended while disconnected so a commit in flight isn't lost.
+async fn upstream_supervisor(
+    state: AppState,
+    room: String,
+    room_state: RoomState,
+    mut cmd_rx: tokio::sync::mpsc::Receiver<UpstreamCmd>,
+) {
+    let tx = room_state.src_tx.clone();
+    let mut last_src_lang: Option<&'static str> = None;
+    let mut pending_audio: std::collections::VecDeque<String> = std::collections::VecDeque::new();
+    let mut backoff = INITIAL_BACKOFF;
+    let closed = room_state.close.notified();
+    tokio::pin!(closed);
+
+    'supervise: loop {
+        let (mut upstream_write, mut upstream_read) = match connect_upstream(&state).await {
+            Ok(pair) => {
+                backoff = INITIAL_BACKOFF;
+                pair
+            }
+            Err(e) => {
+                error!("❌ upstream connect failed: {:?}", e);
+                tokio::select! {
+                    biased;
+                    _ = &mut closed => break 'supervise,
+                    _ = tokio::time::sleep(jittered(backoff)) => {}
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue 'supervise;
+            }
+        };
 
-                let audio_b64 = base64::engine::general_purpose::STANDARD.encode(&bin);
-                let pkg = json!({ "type":"input_audio_buffer.append", "audio": audio_b64 });
-                log_upstream_json("input_audio_buffer.append", &pkg);
+        if let Some(src_lang) = last_src_lang {
+            let session_update = session_update_for(src_lang);
+            log_upstream_json("session.update (resumed)", &session_update);
+            let _ = upstream_write
+                .send(tungstenite::Message::Text(session_update.to_string()))
+                .await;
+            for b64 in &pending_audio {
+                let pkg = json!({ "type":"input_audio_buffer.append", "audio": b64 });
                 let _ = upstream_write
                     .send(tungstenite::Message::Text(pkg.to_string()))
                     .await;
             }
+        }
 
-            Message::Close(_) => break,
-            _ => {}
+        let mut upstream_lost = false;
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut closed => break 'supervise,
+                cmd = cmd_rx.recv() => {
+                    match cmd {
+                        None => break 'supervise, // speaker socket closed
+                        Some(UpstreamCmd::Init { src_lang }) => {
+                            last_src_lang = Some(src_lang);
+                            pending_audio.clear();
+                            let session_update = session_update_for(src_lang);
+                            log_upstream_json("session.update", &session_update);
+                            let _ = upstream_write
+                                .send(tungstenite::Message::Text(session_update.to_string()))
+                                .await;
+                        }
+                        Some(UpstreamCmd::Audio(bin)) => {
+                            let b64 = base64::engine::general_purpose::STANDARD.encode(&bin);
+                            pending_audio.push_back(b64.clone());
+                            if pending_audio.len() > MAX_PENDING_AUDIO_CHUNKS {
+                                pending_audio.pop_front();
+                            }
+                            let pkg = json!({ "type":"input_audio_buffer.append", "audio": b64 });
+                            log_upstream_json("input_audio_buffer.append", &pkg);
+                            let _ = upstream_write
+                                .send(tungstenite::Message::Text(pkg.to_string()))
+                                .await;
+                        }
+                        Some(UpstreamCmd::Commit) => {
+                            let commit = json!({ "type": "input_audio_buffer.commit" });
+                            log_upstream_json("input_audio_buffer.commit", &commit);
+                            let _ = upstream_write
+                                .send(tungstenite::Message::Text(commit.to_string()))
+                                .await;
+                            pending_audio.clear();
+                        }
+                        Some(UpstreamCmd::Cancel) => {
+                            let cancel = json!({ "type": "response.cancel" });
+                            log_upstream_json("response.cancel", &cancel);
+                            let _ = upstream_write
+                                .send(tungstenite::Message::Text(cancel.to_string()))
+                                .await;
+                        }
+                    }
+                }
+                msg = upstream_read.next() => {
+                    match msg {
+                        Some(Ok(tungstenite::Message::Text(txt))) => {
+                            handle_upstream_event(&txt, &room, &tx, &room_state, &state).await;
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => {
+                            error!("upstream read error: {}", e);
+                            upstream_lost = true;
+                            break;
+                        }
+                        None => {
+                            error!("upstream closed");
+                            upstream_lost = true;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        if !upstream_lost {
+            break 'supervise;
         }
+        let _ = tx.send(json!({"type":"status","state":"reconnecting"}).to_string());
+        tokio::select! {
+            biased;
+            _ = &mut closed => break 'supervise,
+            _ = tokio::time::sleep(jittered(backoff)) => {}
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
     }
+}
 
-    let _ = ws_tx.send(Message::Close(None)).await;
-    let _ = reader.await;
+// Parses one upstream Realtime event and forwards/persists finalized
+// transcript segments. Shared between the initial connection and every
+// reconnect so both paths stamp/persist seq numbers identically.
+async fn handle_upstream_event(
+    txt: &str,
+    room: &str,
+    tx: &Sender<String>,
+    room_state: &RoomState,
+    state: &AppState,
+) {
+    let Ok(v) = serde_json::from_str::<Value>(txt) else {
+        return;
+    };
+    let t = v.get("type").and_then(|x| x.as_str()).unwrap_or("-");
+
+    match t {
+        "conversation.item.input_audio_transcription.delta" => {
+            if let Some(delta) = v.get("delta").and_then(|x| x.as_str()) {
+                room_state
+                    .last_activity
+                    .store(now_unix(), Ordering::Relaxed);
+                let _ = tx.send(json!({"type":"partial","src": delta}).to_string());
+            }
+        }
+        "conversation.item.input_audio_transcription.completed" => {
+            if let Some(transcript) = v.get("transcript").and_then(|x| x.as_str()) {
+                room_state
+                    .last_activity
+                    .store(now_unix(), Ordering::Relaxed);
+                info!("← transcript: {}", transcript);
+                let seq = room_state.next_seq.fetch_add(1, Ordering::SeqCst) + 1;
+                let ts = now_unix();
+                let _ = tx.send(json!({"type":"final","seq":seq,"src": transcript}).to_string());
+                if let Some(store) = &state.persist {
+                    if let Err(e) = store.insert_segment(room, seq, ts, transcript).await {
+                        error!(
+                            "failed to persist segment room={} seq={}: {:?}",
+                            room, seq, e
+                        );
+                    }
+                }
+            }
+        }
+        "error" => {
+            error!("← error: {}", txt);
+            let _ = tx.send(json!({"type":"error","data": v}).to_string());
+        }
+        _ => { /* verbose silenced */ }
+    }
 }
 
 // Helper logging (redact base64 body size)